@@ -2,6 +2,7 @@
 mod standalone;
 
 use standalone::bus;
+use standalone::stops;
 
 // Default Command 
 #[tauri::command]
@@ -18,10 +19,16 @@ pub fn run() {
                 .build(),
         )
         .plugin(tauri_plugin_opener::init())
+        .manage(stops::WatchRegistry::default())
+        .manage(bus::BusStopCache::default())
         .invoke_handler(tauri::generate_handler![
             greet,
             // bus::init_bus_database,
-            bus::fetch_bus_data_from_api
+            bus::fetch_bus_data_from_api,
+            bus::find_nearest_stops,
+            stops::fetch_stop_data,
+            stops::watch_stop_data,
+            stops::unwatch_stop_data
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
@@ -2,18 +2,87 @@
 // #[command] is what make them visible to the frontend
 use tauri::command;
 use tauri::http::response;
+use tauri::State;
 use tauri_plugin_sql::{Migration, MigrationKind};
 
-use reqwest::header::{HeaderMap, HeaderValue};
-use reqwest::Client;
+use reqwest::header::{HeaderMap, HeaderValue, InvalidHeaderValue};
+use reqwest::{Client, RequestBuilder, Response, StatusCode};
 
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
+use thiserror::Error;
 
 use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
 
 use dotenv::dotenv;
 
+/// Errors surfaced from `standalone::bus`.
+///
+/// Kept structured (rather than `String`) so callers can tell an HTTP status
+/// failure such as `ApiStatus(429)` apart from network and parse failures,
+/// and so the frontend can show a targeted message instead of a generic
+/// failure string.
+#[derive(Debug, Error)]
+pub enum BusError {
+    #[error("LTA_API_KEY not found in .env file. Please add it to your .env file.")]
+    MissingApiKey,
+
+    #[error("invalid LTA_API_KEY: {0}")]
+    InvalidApiKey(#[from] InvalidHeaderValue),
+
+    #[error("API request failed: {0}")]
+    Http(#[from] reqwest::Error),
+
+    #[error("API returned error: {0}")]
+    ApiStatus(StatusCode),
+
+    #[error("failed to parse JSON response: {source} | body: {body}")]
+    Parse {
+        body: String,
+        source: serde_json::Error,
+    },
+}
+
+/// Wire representation of [`BusError`] sent to the frontend: a `kind`
+/// discriminant plus whatever detail that variant carries, so the UI can
+/// `switch` on `kind` instead of matching English error text.
+#[derive(Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+enum BusErrorPayload<'a> {
+    MissingApiKey,
+    InvalidApiKey { message: String },
+    Http { message: String },
+    ApiStatus { status: u16 },
+    Parse { body: &'a str, message: String },
+}
+
+impl Serialize for BusError {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        let payload = match self {
+            BusError::MissingApiKey => BusErrorPayload::MissingApiKey,
+            BusError::InvalidApiKey(e) => BusErrorPayload::InvalidApiKey {
+                message: e.to_string(),
+            },
+            BusError::Http(e) => BusErrorPayload::Http {
+                message: e.to_string(),
+            },
+            BusError::ApiStatus(status) => BusErrorPayload::ApiStatus {
+                status: status.as_u16(),
+            },
+            BusError::Parse { body, source } => BusErrorPayload::Parse {
+                body,
+                message: source.to_string(),
+            },
+        };
+        payload.serialize(serializer)
+    }
+}
+
 // API Response structures
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct BusStopResponse {
@@ -82,6 +151,215 @@ pub struct TbrInner {
     pub sun_flb: Value,
 }
 
+/// The main struct representing a `BusArrival` API response.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct BusArrivalResponse {
+    pub bus_stop_code: String,
+    pub services: Vec<Service>,
+}
+
+/// Represents a single bus service with its next bus arrival times.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct Service {
+    pub service_no: String,
+    pub operator: String,
+    pub next_bus: Option<NextBus>,
+    pub next_bus2: Option<NextBus>,
+    pub next_bus3: Option<NextBus>,
+}
+
+/// Represents the details for a single bus arrival.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct NextBus {
+    pub origin_code: String,
+    pub destination_code: String,
+    pub estimated_arrival: String,
+    pub monitored: u8,
+    pub latitude: String,
+    pub longitude: String,
+    pub visit_number: String,
+    pub load: String,
+    pub feature: String,
+    #[serde(rename = "Type")] // Handle a custom field name that is already PascalCase
+    pub bus_type: String,
+}
+
+const LTA_BASE_URL: &str = "https://datamall2.mytransport.sg/ltaodataservice";
+const PAGE_SIZE: usize = 500;
+
+const DEFAULT_MAX_RETRIES: u32 = 5;
+const BASE_BACKOFF_MS: u64 = 500;
+const MAX_BACKOFF_MS: u64 = 8_000;
+
+/// Exponential backoff with jitter for a given (1-indexed) retry attempt.
+fn backoff_delay(attempt: u32) -> Duration {
+    let exponential = BASE_BACKOFF_MS.saturating_mul(1 << attempt.saturating_sub(1).min(31));
+    let capped = exponential.min(MAX_BACKOFF_MS);
+    let jitter_ms = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.subsec_millis() as u64 % 250)
+        .unwrap_or(0);
+    Duration::from_millis(capped + jitter_ms)
+}
+
+/// Thin typed wrapper around the LTA DataMall API.
+///
+/// Holds a single `reqwest::Client` and the resolved `LTA_API_KEY` so callers
+/// stop re-reading `.env` and rebuilding a client on every request. New
+/// DataMall endpoints (e.g. BusServices, PassengerVolume) can be added here
+/// as additional methods without duplicating the request plumbing.
+pub struct LtaClient {
+    client: Client,
+    api_key: String,
+    max_retries: u32,
+}
+
+impl LtaClient {
+    /// Loads `.env` (if present) and resolves `LTA_API_KEY` once. A missing
+    /// `.env` file is not an error: `LTA_API_KEY` may already be set in the
+    /// process environment (e.g. in production deployments).
+    pub fn new() -> Result<Self, BusError> {
+        let _ = dotenv();
+        let api_key = std::env::var("LTA_API_KEY").map_err(|_| BusError::MissingApiKey)?;
+
+        Ok(Self {
+            client: Client::new(),
+            api_key,
+            max_retries: DEFAULT_MAX_RETRIES,
+        })
+    }
+
+    /// Overrides the default number of attempts (the first send included)
+    /// made for `429`/`5xx` responses.
+    pub fn with_max_retries(mut self, max_retries: u32) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
+
+    fn headers(&self) -> Result<HeaderMap, BusError> {
+        let mut headers = HeaderMap::new();
+        headers.insert("AccountKey", HeaderValue::from_str(&self.api_key)?);
+        Ok(headers)
+    }
+
+    /// Sends `request`, retrying on `429 Too Many Requests` and `5xx` responses
+    /// with exponential backoff. Any other response is returned immediately; a
+    /// retryable response is returned as-is once `max_retries` attempts (the
+    /// first send included) have been made. The caller still checks
+    /// `response.status()` for non-2xx outcomes.
+    async fn send_with_retry(&self, request: RequestBuilder) -> Result<Response, BusError> {
+        let mut attempt = 0;
+        loop {
+            attempt += 1;
+
+            let response = request
+                .try_clone()
+                .expect("LtaClient only issues bodiless GET requests")
+                .send()
+                .await?;
+
+            let status = response.status();
+            let retryable = status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error();
+
+            if !retryable || attempt >= self.max_retries {
+                return Ok(response);
+            }
+
+            tokio::time::sleep(backoff_delay(attempt)).await;
+        }
+    }
+
+    /// Fetches every bus stop, paging through `$skip` until a short page comes back.
+    pub async fn get_bus_stops(&self) -> Result<Vec<BusStopData>, BusError> {
+        let mut stops = Vec::new();
+        let mut skip = 0;
+
+        loop {
+            let url = format!("{LTA_BASE_URL}/BusStops?$skip={skip}");
+
+            let response = self
+                .send_with_retry(self.client.get(&url).headers(self.headers()?))
+                .await?;
+
+            if !response.status().is_success() {
+                return Err(BusError::ApiStatus(response.status()));
+            }
+
+            let body = response.text().await?;
+            let page: BusStopResponse =
+                serde_json::from_str(&body).map_err(|source| BusError::Parse { body, source })?;
+
+            let batch_size = page.value.len();
+            stops.extend(page.value);
+
+            if batch_size < PAGE_SIZE {
+                break;
+            }
+            skip += PAGE_SIZE;
+        }
+
+        Ok(stops)
+    }
+
+    /// Fetches every bus route, paging through `$skip` until a short page comes back.
+    pub async fn get_bus_routes(&self) -> Result<Vec<BusRouteData>, BusError> {
+        let mut routes = Vec::new();
+        let mut skip = 0;
+
+        loop {
+            let url = format!("{LTA_BASE_URL}/BusRoutes?$skip={skip}");
+
+            let response = self
+                .send_with_retry(self.client.get(&url).headers(self.headers()?))
+                .await?;
+
+            if !response.status().is_success() {
+                return Err(BusError::ApiStatus(response.status()));
+            }
+
+            let body = response.text().await?;
+            let page: BusRouteResponse =
+                serde_json::from_str(&body).map_err(|source| BusError::Parse { body, source })?;
+
+            let batch_size = page.value.len();
+            routes.extend(page.value);
+
+            if batch_size < PAGE_SIZE {
+                break;
+            }
+            skip += PAGE_SIZE;
+        }
+
+        Ok(routes)
+    }
+
+    /// Fetches live arrivals for a bus stop, optionally narrowed to a single service.
+    pub async fn get_arrival(
+        &self,
+        bus_stop_code: &str,
+        service_no: Option<&str>,
+    ) -> Result<BusArrivalResponse, BusError> {
+        let mut url = format!("{LTA_BASE_URL}/v3/BusArrival?BusStopCode={bus_stop_code}");
+        if let Some(service_no) = service_no {
+            url.push_str(&format!("&ServiceNo={service_no}"));
+        }
+
+        let response = self
+            .send_with_retry(self.client.get(&url).headers(self.headers()?))
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(BusError::ApiStatus(response.status()));
+        }
+
+        let body = response.text().await?;
+        serde_json::from_str(&body).map_err(|source| BusError::Parse { body, source })
+    }
+}
+
 // Creates Database Table
 pub fn get_migrations() -> Vec<Migration> {
     vec![
@@ -121,134 +399,141 @@ pub fn get_migrations() -> Vec<Migration> {
 
 // Fetch bus-stop data from LTA API (database operations will be handled by frontend)
 #[command]
-pub async fn fetch_bus_data_from_api() -> Result<HashMap<String, TbrOuter>, String> {
-    let client = Client::new();
-    let mut headers = HeaderMap::new();
+pub async fn fetch_bus_data_from_api() -> Result<HashMap<String, TbrOuter>, BusError> {
+    let client = LtaClient::new()?;
     let mut tbr: HashMap<String, TbrOuter> = HashMap::new();
 
-    // Load .env file and get API key
-    dotenv().map_err(|e| format!("Failed to load .env file: {}", e))?;
-    let api_key = std::env::var("LTA_API_KEY").map_err(|_| {
-        "LTA_API_KEY not found in .env file. Please add it to your .env file.".to_string()
-    })?;
-
-    headers.insert(
-        "AccountKey",
-        HeaderValue::from_str(&api_key).map_err(|e| e.to_string())?,
-    );
-
-    let mut skip = 0;
-    const BATCH_SIZE: usize = 500;
-
-    // LTA API uses pagination
-    loop {
-        let url = format!(
-            "https://datamall2.mytransport.sg/ltaodataservice/BusStops?$skip={}",
-            skip
+    for bus_stop in client.get_bus_stops().await? {
+        tbr.insert(
+            bus_stop.bus_stop_code,
+            TbrOuter {
+                road_name: bus_stop.road_name,
+                desc: bus_stop.description,
+                latitude: bus_stop.latitude,
+                longitude: bus_stop.longitude,
+                buses: HashMap::new(),
+            },
         );
+    }
 
-        let response = client
-            .get(&url)
-            .headers(headers.clone())
-            .send()
-            .await
-            .map_err(|e| format!("API request failed: {}", e))?;
-
-        if !response.status().is_success() {
-            return Err(format!("API returned error: {}", response.status()));
-        }
-
-        let bus_response: BusStopResponse = response
-            .json()
-            .await
-            .map_err(|e| format!("Failed to parse JSON: {}", e))?;
-
-        let batch_size = bus_response.value.len();
-
-        for bus_stop in bus_response.value {
-            tbr.insert(
-                bus_stop.bus_stop_code,
-                TbrOuter {
-                    road_name: bus_stop.road_name,
-                    desc: bus_stop.description,
-                    latitude: bus_stop.latitude,
-                    longitude: bus_stop.longitude,
-                    buses: HashMap::new(),
+    for bus_route in client.get_bus_routes().await? {
+        let bus_code = bus_route.bus_stop_code;
+        let bus_number = bus_route.service_no;
+        if let Some(tbr_outer) = tbr.get_mut(&bus_code) {
+            tbr_outer.buses.insert(
+                bus_number,
+                TbrInner {
+                    operator: bus_route.operator,
+                    stop_seq: bus_route.stop_sequence,
+                    wd_flb: json! ({
+                        "fb": bus_route.wd_first_bus,
+                        "lb": bus_route.wd_last_bus
+                    }),
+                    sat_flb: json! ({
+                        "fb": bus_route.sat_first_bus,
+                        "lb": bus_route.sat_last_bus
+                    }),
+                    sun_flb: json! ({
+                        "fb": bus_route.sun_first_bus,
+                        "lb": bus_route.sun_last_bus
+                    }),
                 },
             );
         }
-
-        // If we got less than expected, we've reached the end
-        if batch_size < BATCH_SIZE {
-            break;
-        }
-
-        skip += BATCH_SIZE;
     }
 
-    skip = 0;
+    Ok(tbr)
+}
 
-    loop {
-        let url = format!(
-            "https://datamall2.mytransport.sg/ltaodataservice/BusRoutes?$skip={}",
-            skip
-        );
+const EARTH_RADIUS_METRES: f64 = 6_371_000.0;
+const MAX_NEAREST_STOPS: usize = 50;
 
-        let response = client
-            .get(&url)
-            .headers(headers.clone())
-            .send()
-            .await
-            .map_err(|e| format!("API request failed: {}", e))?;
+/// Bus stops rarely change, so the cached list only needs an occasional refresh.
+const STOP_CACHE_TTL: Duration = Duration::from_secs(6 * 60 * 60);
 
-        if !response.status().is_success() {
-            return Err(format!("API returned error: {}", response.status()));
-        }
+/// Caches the full bus-stop list behind a TTL so hot-path commands like
+/// `find_nearest_stops` don't re-download all ~5,000 Singapore bus stops
+/// (~10 paginated, retry-wrapped requests) on every call.
+#[derive(Default)]
+pub struct BusStopCache {
+    inner: Mutex<Option<(Instant, Vec<BusStopData>)>>,
+}
 
-        let body_text = response.text().await
-            .map_err(|e| format!("Failed to read response body: {}", e))?;
-        
-        let bus_route_response: BusRouteResponse = serde_json::from_str(&body_text)
-            .map_err(|e| format!("Failed to parse JSON: {} | Body: {}", e, body_text))?;
-
-
-        let batch_size = bus_route_response.value.len();
-
-        for bus_route in bus_route_response.value {
-            let bus_code = bus_route.bus_stop_code;
-            let bus_number = bus_route.service_no;
-            if let Some(tbr_outer) = tbr.get_mut(&bus_code) {
-                tbr_outer.buses.insert(
-                    bus_number,
-                    TbrInner {
-                        operator: bus_route.operator,
-                        stop_seq: bus_route.stop_sequence,
-                        wd_flb: json! ({
-                            "fb": bus_route.wd_first_bus,
-                            "lb": bus_route.wd_last_bus
-                        }),
-                        sat_flb: json! ({
-                            "fb": bus_route.sat_first_bus,
-                            "lb": bus_route.sat_last_bus
-                        }),
-                        sun_flb: json! ({
-                            "fb": bus_route.sun_first_bus,
-                            "lb": bus_route.sun_last_bus
-                        }),
-                    },
-                );
-            }
-        }
+impl BusStopCache {
+    async fn get_or_fetch(&self, client: &LtaClient) -> Result<Vec<BusStopData>, BusError> {
+        let cached = self.inner.lock().unwrap().as_ref().and_then(|(fetched_at, stops)| {
+            (fetched_at.elapsed() < STOP_CACHE_TTL).then(|| stops.clone())
+        });
 
-        // If we got less than expected, we've reached the end
-        if batch_size < BATCH_SIZE {
-            break;
+        if let Some(stops) = cached {
+            return Ok(stops);
         }
 
-        skip += BATCH_SIZE;
+        let stops = client.get_bus_stops().await?;
+        *self.inner.lock().unwrap() = Some((Instant::now(), stops.clone()));
+        Ok(stops)
     }
+}
 
-    Ok(tbr)
+/// A bus stop paired with its great-circle distance from a queried point.
+#[derive(Debug, Serialize)]
+pub struct NearestStop {
+    pub bus_stop_code: String,
+    pub road_name: String,
+    pub description: String,
+    pub latitude: f64,
+    pub longitude: f64,
+    pub distance_metres: f64,
+}
+
+/// Great-circle distance between two lat/long points, in metres (haversine formula).
+fn haversine_distance_metres(lat1: f64, lon1: f64, lat2: f64, lon2: f64) -> f64 {
+    let d_phi = (lat2 - lat1).to_radians();
+    let d_lambda = (lon2 - lon1).to_radians();
+
+    let a = (d_phi / 2.0).sin().powi(2)
+        + lat1.to_radians().cos() * lat2.to_radians().cos() * (d_lambda / 2.0).sin().powi(2);
+    let c = 2.0 * a.sqrt().atan2((1.0 - a).sqrt());
+
+    EARTH_RADIUS_METRES * c
+}
+
+/// Sorts `stops` by distance from `(lat, lon)` and keeps the closest `limit`.
+/// Stops with invalid/zero coordinates are skipped; `limit` is capped at
+/// `MAX_NEAREST_STOPS` so a careless caller can't pull the whole dataset.
+fn nearest_stops(stops: Vec<BusStopData>, lat: f64, lon: f64, limit: usize) -> Vec<NearestStop> {
+    let mut nearest: Vec<NearestStop> = stops
+        .into_iter()
+        .filter(|stop| !(stop.latitude == 0.0 || stop.longitude == 0.0))
+        .map(|stop| NearestStop {
+            distance_metres: haversine_distance_metres(lat, lon, stop.latitude, stop.longitude),
+            bus_stop_code: stop.bus_stop_code,
+            road_name: stop.road_name,
+            description: stop.description,
+            latitude: stop.latitude,
+            longitude: stop.longitude,
+        })
+        .collect();
+
+    nearest.sort_by(|a, b| a.distance_metres.total_cmp(&b.distance_metres));
+    nearest.truncate(limit.min(MAX_NEAREST_STOPS));
+
+    nearest
+}
+
+/// Finds the `limit` bus stops closest to `(lat, lon)`, sorted nearest-first.
+/// Reads the bus-stop list through [`BusStopCache`] so repeated calls (e.g. as
+/// the user moves) don't re-download the whole dataset each time.
+#[command]
+pub async fn find_nearest_stops(
+    lat: f64,
+    lon: f64,
+    limit: usize,
+    cache: State<'_, BusStopCache>,
+) -> Result<Vec<NearestStop>, BusError> {
+    let client = LtaClient::new()?;
+    let stops = cache.get_or_fetch(&client).await?;
+    Ok(nearest_stops(stops, lat, lon, limit))
 }
 
 #[cfg(test)]
@@ -274,4 +559,127 @@ mod tests {
 
         println!("Successfully wrote bus data to bus_data.json");
     }
+
+    #[test]
+    fn backoff_delay_grows_exponentially_before_cap() {
+        let first = backoff_delay(1).as_millis();
+        let second = backoff_delay(2).as_millis();
+        let third = backoff_delay(3).as_millis();
+
+        // Jitter adds up to 250ms on top of the exponential base.
+        assert!((BASE_BACKOFF_MS as u128..BASE_BACKOFF_MS as u128 + 250).contains(&first));
+        assert!(
+            (BASE_BACKOFF_MS as u128 * 2..BASE_BACKOFF_MS as u128 * 2 + 250).contains(&second)
+        );
+        assert!(
+            (BASE_BACKOFF_MS as u128 * 4..BASE_BACKOFF_MS as u128 * 4 + 250).contains(&third)
+        );
+    }
+
+    #[test]
+    fn backoff_delay_is_capped_at_max_backoff() {
+        let delay = backoff_delay(10).as_millis();
+        assert!((MAX_BACKOFF_MS as u128..MAX_BACKOFF_MS as u128 + 250).contains(&delay));
+    }
+
+    #[test]
+    fn haversine_distance_is_zero_for_same_point() {
+        let distance = haversine_distance_metres(1.3521, 103.8198, 1.3521, 103.8198);
+        assert!(distance.abs() < 1e-6);
+    }
+
+    #[test]
+    fn haversine_distance_matches_known_city_pair() {
+        // Singapore to Kuala Lumpur is roughly 315km as the crow flies.
+        let distance = haversine_distance_metres(1.3521, 103.8198, 3.1390, 101.6869);
+        assert!((300_000.0..330_000.0).contains(&distance));
+    }
+
+    fn stop(code: &str, latitude: f64, longitude: f64) -> BusStopData {
+        BusStopData {
+            bus_stop_code: code.to_string(),
+            road_name: "Road".to_string(),
+            description: "Description".to_string(),
+            latitude,
+            longitude,
+        }
+    }
+
+    #[test]
+    fn nearest_stops_skips_zero_coordinates() {
+        let stops = vec![
+            stop("1", 1.30, 103.80),
+            stop("2", 0.0, 0.0),
+        ];
+
+        let result = nearest_stops(stops, 1.30, 103.80, 10);
+
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].bus_stop_code, "1");
+    }
+
+    #[test]
+    fn nearest_stops_skips_single_axis_zero_coordinates() {
+        let stops = vec![
+            stop("valid", 1.30, 103.80),
+            stop("bad-lat", 0.0, 103.80),
+            stop("bad-lon", 1.30, 0.0),
+        ];
+
+        let result = nearest_stops(stops, 1.30, 103.80, 10);
+
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].bus_stop_code, "valid");
+    }
+
+    #[test]
+    fn nearest_stops_sorts_by_distance_ascending() {
+        let stops = vec![
+            stop("far", 1.40, 103.90),
+            stop("near", 1.3001, 103.8001),
+        ];
+
+        let result = nearest_stops(stops, 1.30, 103.80, 10);
+
+        assert_eq!(result[0].bus_stop_code, "near");
+        assert_eq!(result[1].bus_stop_code, "far");
+    }
+
+    #[test]
+    fn nearest_stops_caps_limit_at_max_nearest_stops() {
+        let stops: Vec<BusStopData> = (0..MAX_NEAREST_STOPS + 20)
+            .map(|i| stop(&i.to_string(), 1.30 + i as f64 * 0.0001, 103.80))
+            .collect();
+
+        let result = nearest_stops(stops, 1.30, 103.80, MAX_NEAREST_STOPS + 20);
+
+        assert_eq!(result.len(), MAX_NEAREST_STOPS);
+    }
+
+    #[test]
+    fn bus_error_api_status_serializes_to_tagged_payload() {
+        let error = BusError::ApiStatus(StatusCode::TOO_MANY_REQUESTS);
+
+        let value = serde_json::to_value(&error).expect("BusError should serialize");
+
+        assert_eq!(
+            value,
+            serde_json::json!({ "kind": "api_status", "status": 429 })
+        );
+    }
+
+    #[test]
+    fn bus_error_parse_serializes_to_tagged_payload_with_body_and_message() {
+        let source = serde_json::from_str::<Value>("not json").unwrap_err();
+        let error = BusError::Parse {
+            body: "not json".to_string(),
+            source,
+        };
+
+        let value = serde_json::to_value(&error).expect("BusError should serialize");
+
+        assert_eq!(value["kind"], "parse");
+        assert_eq!(value["body"], "not json");
+        assert!(value["message"].as_str().is_some_and(|m| !m.is_empty()));
+    }
 }
\ No newline at end of file
@@ -1,49 +1,111 @@
 // Complete bus data package: DB operations + API fetching
 // #[command] is what make them visible to the frontend
-use tauri::command;
+use tauri::{command, AppHandle, Emitter, State};
 
 use chrono::{DateTime, Duration, FixedOffset, Utc};
-use dotenv::dotenv;
-use reqwest::header::{HeaderMap, HeaderValue};
-use reqwest::Client;
-use serde::{Deserialize, Serialize};
-use serde_json::{json, Value};
+use serde::Serialize;
 use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::Duration as StdDuration;
+use tauri::async_runtime::JoinHandle;
+
+use super::bus::{BusError, LtaClient};
+
+/// Crowding reported for a bus, decoded from the LTA `Load` codes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Load {
+    SeatsAvailable,
+    StandingAvailable,
+    LimitedStanding,
+    Unknown,
+}
+
+impl From<&str> for Load {
+    fn from(code: &str) -> Self {
+        match code {
+            "SEA" => Load::SeatsAvailable,
+            "SDA" => Load::StandingAvailable,
+            "LSD" => Load::LimitedStanding,
+            _ => Load::Unknown,
+        }
+    }
+}
 
-/// The main struct representing the entire API response.
-#[derive(Debug, Serialize, Deserialize)]
-#[serde(rename_all = "PascalCase")]
-pub struct BusArrivalResponse {
-    pub bus_stop_code: String,
-    pub services: Vec<Service>,
+/// Vehicle type reported for a bus, decoded from the LTA `Type` codes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BusType {
+    SingleDecker,
+    DoubleDecker,
+    BendyBus,
+    Unknown,
 }
 
-/// Represents a single bus service with its next bus arrival times.
-#[derive(Debug, Serialize, Deserialize)]
-#[serde(rename_all = "PascalCase")]
-pub struct Service {
-    pub service_no: String,
-    pub operator: String,
-    pub next_bus: Option<NextBus>,
-    pub next_bus2: Option<NextBus>,
-    pub next_bus3: Option<NextBus>,
+impl From<&str> for BusType {
+    fn from(code: &str) -> Self {
+        match code {
+            "SD" => BusType::SingleDecker,
+            "DD" => BusType::DoubleDecker,
+            "BD" => BusType::BendyBus,
+            _ => BusType::Unknown,
+        }
+    }
 }
 
-/// Represents the details for a single bus arrival.
-#[derive(Debug, Serialize, Deserialize)]
-#[serde(rename_all = "PascalCase")]
-pub struct NextBus {
-    pub origin_code: String,
-    pub destination_code: String,
-    pub estimated_arrival: String,
-    pub monitored: u8,
-    pub latitude: String,
-    pub longitude: String,
-    pub visit_number: String,
-    pub load: String,
-    pub feature: String,
-    #[serde(rename = "Type")] // Handle a custom field name that is already PascalCase
-    pub bus_type: String,
+/// A single upcoming arrival for one service at a bus stop.
+#[derive(Debug, Clone, Serialize)]
+pub struct ArrivalInfo {
+    pub arrival_time: u32,
+    pub bus_type: BusType,
+    pub load: Load,
+    pub wheelchair_access: bool,
+    /// `true` when the estimate is GPS-tracked (`monitored`), `false` when schedule-based.
+    pub is_live: bool,
+}
+
+/// Arrivals for every service at a bus stop, keyed by service number, each
+/// holding up to three upcoming buses keyed by `next_bus`/`next_bus2`/`next_bus3`.
+pub type StopArrivals = HashMap<String, HashMap<String, ArrivalInfo>>;
+
+/// Event name emitted on every `watch_stop_data` tick.
+const ARRIVAL_UPDATE_EVENT: &str = "bus-arrival-update";
+
+/// Payload emitted alongside [`ARRIVAL_UPDATE_EVENT`].
+#[derive(Debug, Clone, Serialize)]
+struct ArrivalUpdate {
+    subscription_id: String,
+    bus_stop_code: String,
+    arrivals: StopArrivals,
+}
+
+/// Tracks the background polling tasks spawned by `watch_stop_data`, keyed by
+/// subscription id, so `unwatch_stop_data` can cancel one on request.
+#[derive(Default)]
+pub struct WatchRegistry {
+    next_id: AtomicU64,
+    handles: Mutex<HashMap<String, JoinHandle<()>>>,
+}
+
+impl WatchRegistry {
+    fn next_id(&self) -> String {
+        format!("watch-{}", self.next_id.fetch_add(1, Ordering::Relaxed))
+    }
+
+    fn insert(&self, subscription_id: String, handle: JoinHandle<()>) {
+        self.handles.lock().unwrap().insert(subscription_id, handle);
+    }
+
+    fn cancel(&self, subscription_id: &str) -> bool {
+        match self.handles.lock().unwrap().remove(subscription_id) {
+            Some(handle) => {
+                handle.abort();
+                true
+            }
+            None => false,
+        }
+    }
 }
 
 fn get_time_diff(estimated_arrival_str: String, now: DateTime<Utc>) -> Option<u32> {
@@ -73,39 +135,15 @@ fn get_time_diff(estimated_arrival_str: String, now: DateTime<Utc>) -> Option<u3
 
 // Fetch bus-stop data from LTA API (database operations will be handled by frontend)
 #[command]
-pub async fn fetch_stop_data(bus_stop_code: String) -> Result<HashMap<String, Value>, String> {
-    let client = Client::new();
-    let mut headers = HeaderMap::new();
-    let mut tbr: HashMap<String, Value> = HashMap::new();
-
-    dotenv().map_err(|e| format!("Failed to load .env file: {}", e))?;
-    let api_key = std::env::var("LTA_API_KEY")
-        .map_err(|_| "LTA_API_KEY not found in .env file. Please add it to your .env file.".to_string())?;
-
-    headers.insert("AccountKey", HeaderValue::from_str(&api_key).map_err(|e| e.to_string())?);
-
-    let url = format!(
-        "https://datamall2.mytransport.sg/ltaodataservice/v3/BusArrival?BusStopCode={}",
-        bus_stop_code
-    );
-
-    let response = client
-        .get(&url)
-        .headers(headers.clone())
-        .send()
-        .await
-        .map_err(|e| format!("API request failed: {}", e))?;
-
-    if !response.status().is_success() {
-        return Err(format!("API returned error: {}", response.status()));
-    }
+pub async fn fetch_stop_data(
+    bus_stop_code: String,
+    service_no: Option<String>,
+) -> Result<StopArrivals, BusError> {
+    let client = LtaClient::new()?;
+    let mut tbr: StopArrivals = HashMap::new();
 
     let now = Utc::now();
-
-    let bus_response: BusArrivalResponse = response
-        .json()
-        .await
-        .map_err(|e| format!("Failed to parse JSON: {}", e))?;
+    let bus_response = client.get_arrival(&bus_stop_code, service_no.as_deref()).await?;
 
     for service in bus_response.services {
         let mut service_data = HashMap::new();
@@ -121,18 +159,19 @@ pub async fn fetch_stop_data(bus_stop_code: String) -> Result<HashMap<String, Va
                     let key = format!("next_bus{}", if i == 0 { "".to_string() } else { (i + 1).to_string() });
                     service_data.insert(
                         key,
-                        json!({
-                            "arrival_time": minutes,
-                            "type": next_bus_data.bus_type,
-                            "wheelchair_access": next_bus_data.feature == "WAB",
-                            "capacity": next_bus_data.load
-                        }),
+                        ArrivalInfo {
+                            arrival_time: minutes,
+                            bus_type: BusType::from(next_bus_data.bus_type.as_str()),
+                            load: Load::from(next_bus_data.load.as_str()),
+                            wheelchair_access: next_bus_data.feature == "WAB",
+                            is_live: next_bus_data.monitored == 1,
+                        },
                     );
                 }
             }
         }
-        
-        tbr.insert(service.service_no, json!(service_data));
+
+        tbr.insert(service.service_no, service_data);
     }
 
     Ok(tbr)
@@ -141,6 +180,52 @@ pub async fn fetch_stop_data(bus_stop_code: String) -> Result<HashMap<String, Va
 // Note: Database operations (INSERT/SELECT) are handled by frontend with tauri-plugin-sql v2
 // Backend only provides API data fetching
 
+/// Starts polling `bus_stop_code` every `interval_secs`, emitting a
+/// [`ARRIVAL_UPDATE_EVENT`] on each tick with freshly recomputed arrival
+/// minutes. Returns a subscription id that `unwatch_stop_data` accepts to
+/// stop the task.
+#[command]
+pub async fn watch_stop_data(
+    bus_stop_code: String,
+    service_no: Option<String>,
+    interval_secs: u64,
+    app: AppHandle,
+    registry: State<'_, WatchRegistry>,
+) -> Result<String, BusError> {
+    let subscription_id = registry.next_id();
+    let interval = StdDuration::from_secs(interval_secs.max(1));
+
+    let task_subscription_id = subscription_id.clone();
+    let handle = tauri::async_runtime::spawn(async move {
+        loop {
+            match fetch_stop_data(bus_stop_code.clone(), service_no.clone()).await {
+                Ok(arrivals) => {
+                    let _ = app.emit(
+                        ARRIVAL_UPDATE_EVENT,
+                        ArrivalUpdate {
+                            subscription_id: task_subscription_id.clone(),
+                            bus_stop_code: bus_stop_code.clone(),
+                            arrivals,
+                        },
+                    );
+                }
+                Err(e) => eprintln!("watch_stop_data({bus_stop_code}): {e}"),
+            }
+
+            tokio::time::sleep(interval).await;
+        }
+    });
+
+    registry.insert(subscription_id.clone(), handle);
+    Ok(subscription_id)
+}
+
+/// Cancels a subscription previously started by `watch_stop_data`.
+#[command]
+pub fn unwatch_stop_data(subscription_id: String, registry: State<'_, WatchRegistry>) -> bool {
+    registry.cancel(&subscription_id)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -150,7 +235,7 @@ mod tests {
     #[tokio::test]
     async fn test_fetch_stop_data() {
         let bus_stop_code = "1511".to_string();
-        let result = fetch_stop_data(bus_stop_code.clone()).await;
+        let result = fetch_stop_data(bus_stop_code.clone(), None).await;
 
         // Unwrap the successful result and serialize it
         let data = result.unwrap();